@@ -1,26 +1,67 @@
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{Read, BufReader, Seek};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+use crate::game_database;
 
 // NES Cartridge and ROM handling 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mirroring {
     Vertical,
     Horizontal,
     FourScreen,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum RomFormat {
+    INes,
+    Nes20,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChrMode {
+    Rom,
+    Ram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingMode {
+    Ntsc,
+    Pal,
+    MultipleRegion,
+    Dendy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleType {
+    Nes,
+    VsSystem,
+    Playchoice10,
+    Extended,
+}
+
 #[derive(Debug)]
 pub struct NesHeader {
-    pub prg_rom_size: u8, // Number of 16 KB units
-    pub chr_rom_size: u8, // Number of 8 KB units (0 means CHR RAM)
+    pub prg_rom_size: u8, // Number of 16 KB units (raw byte 4)
+    pub chr_rom_size: u8, // Number of 8 KB units (raw byte 5, 0 means CHR RAM)
     pub flags6: u8,
     pub flags7: u8,
-    // pub nes2_indicator: u8, // For NES 2.0 format, not handled initially
-    // pub console_type: u8, // For NES 2.0 / Famiclone types
+
+    pub rom_format: RomFormat,
 
     // Derived from flags
-    pub mapper_number: u8,
+    pub mapper_number: u16, // 12 bits wide under NES 2.0, 8 bits under iNES
+    pub submapper_number: u8, // NES 2.0 only, 0 otherwise
+    pub prg_rom_bytes: u32, // Actual PRG-ROM size in bytes
+    pub chr_rom_bytes: u32, // Actual CHR-ROM size in bytes (0 means CHR RAM)
+    pub prg_ram_shift: u8, // NES 2.0 byte 10 low nibble: size = 64 << n, 0 = none
+    pub prg_nvram_shift: u8, // NES 2.0 byte 10 high nibble
+    pub chr_ram_shift: u8, // NES 2.0 byte 11 low nibble
+    pub chr_nvram_shift: u8, // NES 2.0 byte 11 high nibble
+    pub timing_mode: TimingMode, // NES 2.0 byte 12 bits 0-1, NTSC otherwise
+    pub console_type: ConsoleType, // flags7 bits 0-1
     pub mirroring: Mirroring,
     pub has_battery_backed_ram: bool,
     pub has_trainer: bool, // 512-byte trainer at $7000-$71FF
@@ -37,7 +78,12 @@ impl NesHeader {
         let chr_rom_size = header_bytes[5];
         let flags6 = header_bytes[6];
         let flags7 = header_bytes[7];
-        // Bytes 8-15 are typically padding or NES 2.0 specific, ignored for basic iNES
+
+        let rom_format = if (flags7 & 0x0C) == 0x08 {
+            RomFormat::Nes20
+        } else {
+            RomFormat::INes
+        };
 
         let mirroring = if flags6 & 0x08 != 0 {
             Mirroring::FourScreen
@@ -53,20 +99,158 @@ impl NesHeader {
         // Mapper number is formed by the lower nibble of flags6 and upper nibble of flags7
         let mapper_lower_nibble = flags6 >> 4;
         let mapper_upper_nibble = flags7 & 0xF0; // Same as (flags7 >> 4) << 4
-        let mapper_number = mapper_upper_nibble | mapper_lower_nibble;
+        let mapper_number_8 = mapper_upper_nibble | mapper_lower_nibble;
+
+        let (mapper_number, submapper_number, prg_ram_shift, prg_nvram_shift, chr_ram_shift, chr_nvram_shift, prg_rom_bytes, chr_rom_bytes);
+
+        if rom_format == RomFormat::Nes20 {
+            let byte8 = header_bytes[8];
+            let byte9 = header_bytes[9];
+            let byte10 = header_bytes[10];
+            let byte11 = header_bytes[11];
+
+            let mapper_high_nibble = byte8 & 0x0F;
+            mapper_number = ((mapper_high_nibble as u16) << 8) | (mapper_number_8 as u16);
+            submapper_number = byte8 >> 4;
+
+            prg_ram_shift = byte10 & 0x0F;
+            prg_nvram_shift = byte10 >> 4;
+            chr_ram_shift = byte11 & 0x0F;
+            chr_nvram_shift = byte11 >> 4;
+
+            prg_rom_bytes = Self::decode_rom_size(prg_rom_size, byte9 & 0x0F, 16 * 1024);
+            chr_rom_bytes = Self::decode_rom_size(chr_rom_size, (byte9 >> 4) & 0x0F, 8 * 1024);
+        } else {
+            mapper_number = mapper_number_8 as u16;
+            submapper_number = 0;
+            prg_ram_shift = 0;
+            prg_nvram_shift = 0;
+            chr_ram_shift = 0;
+            chr_nvram_shift = 0;
+            prg_rom_bytes = prg_rom_size as u32 * 16 * 1024;
+            chr_rom_bytes = chr_rom_size as u32 * 8 * 1024;
+        }
+
+        let console_type = match flags7 & 0x03 {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended,
+        };
+
+        let timing_mode = if rom_format == RomFormat::Nes20 {
+            match header_bytes[12] & 0x03 {
+                0 => TimingMode::Ntsc,
+                1 => TimingMode::Pal,
+                2 => TimingMode::MultipleRegion,
+                _ => TimingMode::Dendy,
+            }
+        } else {
+            TimingMode::Ntsc
+        };
 
         Ok(NesHeader {
             prg_rom_size,
             chr_rom_size,
             flags6,
             flags7,
+            rom_format,
             mapper_number,
+            submapper_number,
+            prg_rom_bytes,
+            chr_rom_bytes,
+            prg_ram_shift,
+            prg_nvram_shift,
+            chr_ram_shift,
+            chr_nvram_shift,
+            timing_mode,
+            console_type,
             mirroring,
             has_battery_backed_ram,
             has_trainer,
             four_screen_mode: (flags6 & 0x08) != 0,
         })
     }
+
+    // NES 2.0 PRG/CHR size encoding: the MSB nibble extends the LSB byte's unit
+    // count to 12 bits, unless the nibble is 0xF, in which case the LSB byte
+    // itself is an exponent-multiplier pair: size = 2^exponent * (multiplier*2 + 1).
+    // The exponent is attacker/dump-controlled (6 bits, range 0-63), so this
+    // computes in u64 and saturates to u32::MAX rather than panicking/wrapping
+    // on malformed headers claiming an absurd size.
+    fn decode_rom_size(lsb: u8, msb_nibble: u8, unit_bytes: u32) -> u32 {
+        if msb_nibble == 0x0F {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = (lsb & 0x03) as u64;
+            let size = 2u64.saturating_pow(exponent).saturating_mul(multiplier * 2 + 1);
+            size.min(u32::MAX as u64) as u32
+        } else {
+            let units = ((msb_nibble as u32) << 8) | lsb as u32;
+            units * unit_bytes
+        }
+    }
+
+    /// Serializes back to a 16-byte iNES/NES 2.0 header. Parsing the result
+    /// with `from_bytes` reproduces an equivalent header (parse -> to_bytes
+    /// -> parse is idempotent), except for exponent-multiplier-encoded ROM
+    /// sizes, which are re-encoded as plain unit counts.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+
+        let mapper_number_8 = (self.mapper_number & 0xFF) as u8;
+        let mirroring_bit = if matches!(self.mirroring, Mirroring::Vertical) { 0x01 } else { 0x00 };
+
+        let mut flags6 = (mapper_number_8 & 0x0F) << 4;
+        flags6 |= mirroring_bit;
+        if self.has_battery_backed_ram { flags6 |= 0x02; }
+        if self.has_trainer { flags6 |= 0x04; }
+        if self.four_screen_mode { flags6 |= 0x08; }
+
+        let mut flags7 = mapper_number_8 & 0xF0;
+        flags7 |= match self.console_type {
+            ConsoleType::Nes => 0x00,
+            ConsoleType::VsSystem => 0x01,
+            ConsoleType::Playchoice10 => 0x02,
+            ConsoleType::Extended => 0x03,
+        };
+        if self.rom_format == RomFormat::Nes20 {
+            flags7 = (flags7 & !0x0C) | 0x08;
+        }
+
+        bytes[6] = flags6;
+        bytes[7] = flags7;
+
+        if self.rom_format == RomFormat::Nes20 {
+            let prg_units = ((self.prg_rom_bytes / (16 * 1024)).min(0xFFF)) as u16;
+            let chr_units = ((self.chr_rom_bytes / (8 * 1024)).min(0xFFF)) as u16;
+
+            bytes[4] = (prg_units & 0xFF) as u8;
+            bytes[5] = (chr_units & 0xFF) as u8;
+
+            let mapper_high_nibble = ((self.mapper_number >> 8) & 0x0F) as u8;
+            bytes[8] = (self.submapper_number << 4) | mapper_high_nibble;
+
+            let prg_msb = ((prg_units >> 8) & 0x0F) as u8;
+            let chr_msb = ((chr_units >> 8) & 0x0F) as u8;
+            bytes[9] = (chr_msb << 4) | prg_msb;
+
+            bytes[10] = (self.prg_nvram_shift << 4) | self.prg_ram_shift;
+            bytes[11] = (self.chr_nvram_shift << 4) | self.chr_ram_shift;
+
+            bytes[12] = match self.timing_mode {
+                TimingMode::Ntsc => 0x00,
+                TimingMode::Pal => 0x01,
+                TimingMode::MultipleRegion => 0x02,
+                TimingMode::Dendy => 0x03,
+            };
+        } else {
+            bytes[4] = self.prg_rom_size;
+            bytes[5] = self.chr_rom_size;
+        }
+
+        bytes
+    }
 }
 
 // Placeholder for the main Cartridge struct that will eventually hold the header and ROM data
@@ -74,82 +258,192 @@ impl NesHeader {
 pub struct Cartridge {
     pub header: NesHeader,
     pub prg_rom: Vec<u8>,
-    pub chr_rom: Vec<u8>, // CHR ROM or CHR RAM
+    pub chr_rom: Vec<u8>, // CHR ROM or CHR RAM, depending on `chr_mode`
+    pub chr_mode: ChrMode,
     pub trainer: Option<Vec<u8>>,
+    pub prg_ram: Vec<u8>, // $6000-$7FFF window; battery-backed when header.has_battery_backed_ram
+    pub header_correction_applied: bool, // True when the game database overrode header fields
+    pub playchoice_hint_screen: Option<Vec<u8>>, // 8KB hint-screen block trailing CHR data, PlayChoice-10 only
+    rom_path: Option<String>,
 }
 
 impl Cartridge {
-    pub fn from_file(path: &str) -> Result<Self, String> {
-        let file = File::open(path).map_err(|e| format!("Failed to open ROM file: {}", e))?;
-        let mut reader = BufReader::new(file);
-
+    /// Parses a complete iNES/NES 2.0 ROM image already held in memory. Does
+    /// no filesystem I/O, so callers that already have ROM bytes in hand
+    /// (not read from a path) can skip `from_file` entirely; battery-RAM
+    /// loading, which needs a sidecar `.sav` file, is handled separately by
+    /// `from_file`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 16 {
+            return Err("ROM data is too short to contain an iNES header".to_string());
+        }
         let mut header_bytes = [0u8; 16];
-        reader.read_exact(&mut header_bytes)
-            .map_err(|e| format!("Failed to read iNES header: {}", e))?;
-        
-        // Diagnostic print for raw header bytes
-        println!("DEBUG: Raw iNES Header Bytes: {:02X?}", header_bytes);
+        header_bytes.copy_from_slice(&data[0..16]);
+        let mut offset = 16;
 
-        let header = NesHeader::from_bytes(&header_bytes)?;
-
-        // Diagnostic prints for parsed header values
-        println!("DEBUG: Parsed Header: {:#?}", header);
-        println!("DEBUG: Header PRG ROM Size (units): {}", header.prg_rom_size);
-        println!("DEBUG: Header CHR ROM Size (units): {}", header.chr_rom_size);
-        println!("DEBUG: Header Has Trainer: {}", header.has_trainer);
+        let mut header = NesHeader::from_bytes(&header_bytes)?;
 
         let mut trainer: Option<Vec<u8>> = None;
         if header.has_trainer {
-            println!("DEBUG: Trainer detected, attempting to read 512 bytes for trainer.");
-            let mut trainer_data = vec![0u8; 512];
-            reader.read_exact(&mut trainer_data)
-                .map_err(|e| format!("Failed to read trainer data: {}", e))?;
-            trainer = Some(trainer_data);
-            println!("DEBUG: Successfully read trainer data.");
+            if data.len() < offset + 512 {
+                return Err("ROM data is too short to contain the declared 512-byte trainer".to_string());
+            }
+            trainer = Some(data[offset..offset + 512].to_vec());
+            offset += 512;
         }
 
-        let prg_rom_size_bytes = header.prg_rom_size as usize * 16 * 1024; // 16KB units
-        println!("DEBUG: Calculated PRG ROM size in bytes to read: {}", prg_rom_size_bytes);
-        
+        let prg_rom_size_bytes = header.prg_rom_bytes as usize;
         if prg_rom_size_bytes == 0 {
             return Err("PRG ROM size is 0, which is invalid.".to_string());
         }
-
-        let mut prg_rom = vec![0u8; prg_rom_size_bytes];
-        match reader.read_exact(&mut prg_rom) {
-            Ok(_) => println!("DEBUG: Successfully read PRG ROM data."),
-            Err(e) => {
-                println!("ERROR_DETAIL: Failed during read_exact for PRG ROM: {}", e);
-                // Attempt to get remaining file size for context
-                // This is a bit hacky and might not be perfectly accurate depending on BufReader state
-                let remaining_bytes = reader.buffer().len() as u64 + reader.get_ref().metadata().map_or(0, |m| m.len()) - reader.get_ref().stream_position().map_or(0, |p|p) ;
-                println!("DEBUG: Approximate remaining bytes in file before PRG read attempt: {}", remaining_bytes);
-                return Err(format!("Failed to read PRG ROM: {}", e));
-            }
+        if data.len() < offset + prg_rom_size_bytes {
+            return Err(format!("ROM data is too short for {} bytes of PRG-ROM", prg_rom_size_bytes));
         }
+        let prg_rom = data[offset..offset + prg_rom_size_bytes].to_vec();
+        offset += prg_rom_size_bytes;
 
-        let chr_rom_size_bytes = header.chr_rom_size as usize * 8 * 1024; // 8KB units
-        println!("DEBUG: Calculated CHR ROM size in bytes to read: {}", chr_rom_size_bytes);
-        let mut chr_rom = Vec::new();
+        let chr_rom_size_bytes = header.chr_rom_bytes as usize;
+        let chr_rom_on_disk;
+        let chr_mode;
         if chr_rom_size_bytes > 0 {
-            chr_rom = vec![0u8; chr_rom_size_bytes];
-            reader.read_exact(&mut chr_rom)
-                .map_err(|e| format!("Failed to read CHR ROM: {}", e))?;
+            if data.len() < offset + chr_rom_size_bytes {
+                return Err(format!("ROM data is too short for {} bytes of CHR-ROM", chr_rom_size_bytes));
+            }
+            chr_rom_on_disk = data[offset..offset + chr_rom_size_bytes].to_vec();
+            offset += chr_rom_size_bytes;
+            chr_mode = ChrMode::Rom;
         } else {
-            // If chr_rom_size is 0, it often implies CHR RAM. 
-            // For now, we'll leave chr_rom empty. Some mappers might allocate CHR RAM.
-            // A common size for CHR RAM is 8KB if a game uses it.
-            // For simplicity, we are not allocating CHR RAM here, 
-            // this will be handled by the PPU or mapper logic later.
+            chr_rom_on_disk = Vec::new();
+            chr_mode = ChrMode::Ram;
+        }
+
+        let mut playchoice_hint_screen: Option<Vec<u8>> = None;
+        if header.console_type == ConsoleType::Playchoice10 && data.len() >= offset + 8 * 1024 {
+            playchoice_hint_screen = Some(data[offset..offset + 8 * 1024].to_vec());
         }
 
+        // Hashed over the ROM's on-disk PRG+CHR bytes, matching how No-Intro/
+        // NesCartDB key their entries: CHR-RAM carts contribute zero CHR bytes,
+        // not a zero-padded placeholder (which is allocated below, after hashing).
+        let crc32 = game_database::crc32(&[prg_rom.as_slice(), chr_rom_on_disk.as_slice()].concat());
+        let mut header_correction_applied = false;
+        if let Some(entry) = game_database::lookup(crc32) {
+            header.mapper_number = entry.mapper_number;
+            header.mirroring = entry.mirroring;
+            header.has_battery_backed_ram = entry.has_battery_backed_ram;
+            header_correction_applied = true;
+        }
+
+        let chr_rom = if chr_mode == ChrMode::Rom {
+            chr_rom_on_disk
+        } else {
+            // chr_rom_size == 0 means CHR-RAM: allocate it zeroed so the PPU
+            // has writable pattern memory, sized from the NES 2.0 CHR-RAM
+            // shift field when present, defaulting to the common 8KB.
+            let chr_ram_bytes = if header.rom_format == RomFormat::Nes20 && header.chr_ram_shift > 0 {
+                64usize << header.chr_ram_shift
+            } else {
+                8 * 1024
+            };
+            vec![0u8; chr_ram_bytes]
+        };
+
+        // NES 2.0 headers explicitly declare PRG-(N)VRAM size, including zero
+        // ("none" per the field's doc comment) — only plain iNES, which has no
+        // such field, falls back to the 8KB-typical default.
+        let prg_ram_bytes = if header.rom_format == RomFormat::Nes20 {
+            let ram = if header.prg_ram_shift > 0 { 64usize << header.prg_ram_shift } else { 0 };
+            let nvram = if header.prg_nvram_shift > 0 { 64usize << header.prg_nvram_shift } else { 0 };
+            ram + nvram
+        } else {
+            8 * 1024 // Typical PRG-RAM size for plain iNES files
+        };
+        let prg_ram = vec![0u8; prg_ram_bytes];
+
         Ok(Cartridge {
             header,
             prg_rom,
             chr_rom,
+            chr_mode,
             trainer,
+            prg_ram,
+            header_correction_applied,
+            playchoice_hint_screen,
+            rom_path: None,
         })
     }
+
+    /// Whether CHR memory is writable, so the mapper layer can gate `ppu_write`.
+    pub fn chr_is_writable(&self) -> bool {
+        self.chr_mode == ChrMode::Ram
+    }
+}
+
+#[cfg(feature = "std")]
+impl Cartridge {
+    /// Reads a whole ROM file from disk and parses it via `from_bytes`, then
+    /// loads battery-backed PRG-RAM from a sidecar `.sav` file if present.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("Failed to read ROM file {}: {}", path, e))?;
+        let mut cartridge = Self::from_bytes(&data)?;
+        cartridge.rom_path = Some(path.to_string());
+
+        if cartridge.header.has_battery_backed_ram {
+            let sav_path = Self::battery_save_path(path);
+            // No existing .sav file yet is not an error: start with blank PRG-RAM.
+            if let Ok(mut sav_file) = File::open(&sav_path) {
+                let len = cartridge.prg_ram.len().min(sav_file.metadata().map_or(0, |m| m.len() as usize));
+                sav_file
+                    .read_exact(&mut cartridge.prg_ram[..len])
+                    .map_err(|e| format!("Failed to read .sav file {}: {}", sav_path, e))?;
+            }
+        }
+
+        Ok(cartridge)
+    }
+
+    /// Writes this cartridge back out as an iNES/NES 2.0 file: header, optional
+    /// trainer, PRG-ROM, then CHR-ROM in spec order. CHR-RAM is not written,
+    /// since the header declares zero CHR-ROM bytes for it.
+    pub fn save_to_file(&self, path: &str) -> Result<(), String> {
+        let mut file = File::create(path).map_err(|e| format!("Failed to create ROM file {}: {}", path, e))?;
+
+        file.write_all(&self.header.to_bytes())
+            .map_err(|e| format!("Failed to write header: {}", e))?;
+
+        if let Some(trainer) = &self.trainer {
+            file.write_all(trainer).map_err(|e| format!("Failed to write trainer: {}", e))?;
+        }
+
+        file.write_all(&self.prg_rom).map_err(|e| format!("Failed to write PRG ROM: {}", e))?;
+
+        if self.chr_mode == ChrMode::Rom {
+            file.write_all(&self.chr_rom).map_err(|e| format!("Failed to write CHR ROM: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    fn battery_save_path(rom_path: &str) -> String {
+        match rom_path.rsplit_once('.') {
+            Some((stem, _ext)) => format!("{}.sav", stem),
+            None => format!("{}.sav", rom_path),
+        }
+    }
+
+    /// Writes the current PRG-RAM contents out to the ROM's sidecar `.sav`
+    /// file. No-op if the cartridge has no battery-backed RAM; returns an
+    /// error if it has battery-backed RAM but was not loaded from a file.
+    pub fn save_battery_ram(&self) -> Result<(), String> {
+        if !self.header.has_battery_backed_ram {
+            return Ok(());
+        }
+        let rom_path = self.rom_path.as_ref().ok_or("Cartridge has no backing file to save alongside")?;
+        let sav_path = Self::battery_save_path(rom_path);
+        let mut file = File::create(&sav_path).map_err(|e| format!("Failed to create .sav file {}: {}", sav_path, e))?;
+        file.write_all(&self.prg_ram).map_err(|e| format!("Failed to write .sav file {}: {}", sav_path, e))?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -239,4 +533,209 @@ mod tests {
         assert!(matches!(header_vertical.mirroring, Mirroring::Vertical));
         assert!(!header_vertical.four_screen_mode);
     }
+
+    #[test]
+    fn test_nes20_header_parsing() {
+        // NES 2.0: mapper 0x221, submapper 2, 3*16KB PRG, 1*8KB CHR,
+        // PRG-RAM shift 7 (64 << 7 = 8KB), no PRG-NVRAM, no CHR-RAM/NVRAM.
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A, // "NES\x1A"
+            0x03,                   // PRG ROM LSB: 3 units
+            0x01,                   // CHR ROM LSB: 1 unit
+            0b00010000,             // Flags 6: Mapper lower nibble 1
+            0b00101000,             // Flags 7: Mapper upper nibble 2, NES 2.0 identifier (bits 2-3 = 10)
+            0x22,                   // Byte 8: Submapper 2 (high nibble), mapper bits 8-11 = 2 (low nibble)
+            0x00,                   // Byte 9: PRG/CHR size MSB nibbles, both 0
+            0x07,                   // Byte 10: PRG-RAM shift 7, PRG-NVRAM shift 0
+            0x00,                   // Byte 11: CHR-RAM shift 0, CHR-NVRAM shift 0
+            0x00, 0x00, 0x00, 0x00
+        ];
+
+        let header = NesHeader::from_bytes(&header_data).unwrap();
+
+        assert_eq!(header.rom_format, RomFormat::Nes20);
+        assert_eq!(header.mapper_number, 0x221);
+        assert_eq!(header.submapper_number, 2);
+        assert_eq!(header.prg_rom_bytes, 3 * 16 * 1024);
+        assert_eq!(header.chr_rom_bytes, 8 * 1024);
+        assert_eq!(header.prg_ram_shift, 7);
+        assert_eq!(header.prg_nvram_shift, 0);
+        assert_eq!(header.chr_ram_shift, 0);
+        assert_eq!(header.chr_nvram_shift, 0);
+    }
+
+    #[test]
+    fn test_nes20_exponent_multiplier_rom_size() {
+        // When a size's MSB nibble is 0xF, the LSB byte is an exponent-multiplier
+        // pair instead of a unit count: size = 2^exponent * (multiplier*2 + 1).
+        // See the per-byte comments below for this test's exponent/multiplier values.
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0b0000_1001,            // PRG LSB: exponent 2, multiplier 1 -> 3 * 2^2 = 12 bytes
+            0b0000_0101,            // CHR LSB: exponent 1, multiplier 1 -> 3 * 2^1 = 6 bytes
+            0x00,
+            0b00001000,             // Flags 7: NES 2.0 identifier, mapper 0
+            0x00,
+            0xFF,                   // Byte 9: both MSB nibbles are 0xF (exponent-multiplier mode)
+            0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+
+        let header = NesHeader::from_bytes(&header_data).unwrap();
+
+        assert_eq!(header.rom_format, RomFormat::Nes20);
+        assert_eq!(header.prg_rom_bytes, 12);
+        assert_eq!(header.chr_rom_bytes, 6);
+    }
+
+    #[test]
+    fn test_nes20_exponent_multiplier_rom_size_saturates_on_overflow() {
+        // PRG LSB 0x85 -> exponent 0x21 (33), multiplier 1 -> 3 * 2^33 bytes,
+        // which overflows u32. This must saturate to u32::MAX, not panic or wrap.
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0x85,                   // PRG LSB: exponent 33, multiplier 1
+            0x00,
+            0x00,
+            0b00001000,             // Flags 7: NES 2.0 identifier, mapper 0
+            0x00,
+            0xFF,                   // Byte 9: both MSB nibbles are 0xF (exponent-multiplier mode)
+            0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+
+        let header = NesHeader::from_bytes(&header_data).unwrap();
+
+        assert_eq!(header.rom_format, RomFormat::Nes20);
+        assert_eq!(header.prg_rom_bytes, u32::MAX);
+    }
+
+    #[test]
+    fn test_header_to_bytes_roundtrip() {
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0x02,
+            0x01,
+            0b00010111, // Mapper lower 1, FourScreen, Trainer, Battery, Vertical
+            0b00010000, // Mapper upper 1
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+
+        let header = NesHeader::from_bytes(&header_data).unwrap();
+        let reparsed = NesHeader::from_bytes(&header.to_bytes()).unwrap();
+
+        assert_eq!(reparsed.mapper_number, header.mapper_number);
+        assert_eq!(reparsed.prg_rom_bytes, header.prg_rom_bytes);
+        assert_eq!(reparsed.chr_rom_bytes, header.chr_rom_bytes);
+        assert_eq!(reparsed.has_battery_backed_ram, header.has_battery_backed_ram);
+        assert_eq!(reparsed.has_trainer, header.has_trainer);
+        assert_eq!(reparsed.four_screen_mode, header.four_screen_mode);
+    }
+
+    #[test]
+    fn test_cartridge_from_bytes_in_memory() {
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0x01, // 1 * 16KB PRG
+            0x00, // 0 CHR units -> CHR-RAM
+            0x00,
+            0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+        let mut rom_bytes = header_data.to_vec();
+        rom_bytes.extend(std::iter::repeat_n(0x42, 16 * 1024));
+
+        let cartridge = Cartridge::from_bytes(&rom_bytes).unwrap();
+
+        assert_eq!(cartridge.prg_rom.len(), 16 * 1024);
+        assert_eq!(cartridge.chr_mode, ChrMode::Ram);
+        assert_eq!(cartridge.chr_rom.len(), 8 * 1024);
+        assert!(cartridge.chr_rom.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_cartridge_from_bytes_rejects_truncated_data() {
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0x02, // 2 * 16KB PRG declared, but no PRG data follows
+            0x00,
+            0x00,
+            0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+
+        assert!(Cartridge::from_bytes(&header_data).is_err());
+    }
+
+    #[test]
+    fn test_cartridge_save_and_reload_roundtrip() {
+        let rom_path = std::env::temp_dir().join("rusty_nes_test_roundtrip.nes");
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0x01, // 1 * 16KB PRG
+            0x01, // 1 * 8KB CHR
+            0x00,
+            0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+        let mut rom_bytes = header_data.to_vec();
+        rom_bytes.extend(std::iter::repeat_n(0xAB, 16 * 1024)); // PRG ROM
+        rom_bytes.extend(std::iter::repeat_n(0xCD, 8 * 1024)); // CHR ROM
+        std::fs::write(&rom_path, &rom_bytes).unwrap();
+
+        let original = Cartridge::from_file(rom_path.to_str().unwrap()).unwrap();
+
+        let resaved_path = std::env::temp_dir().join("rusty_nes_test_roundtrip_resaved.nes");
+        original.save_to_file(resaved_path.to_str().unwrap()).unwrap();
+        let reloaded = Cartridge::from_file(resaved_path.to_str().unwrap()).unwrap();
+
+        assert_eq!(reloaded.prg_rom, original.prg_rom);
+        assert_eq!(reloaded.chr_rom, original.chr_rom);
+        assert_eq!(reloaded.header.mapper_number, original.header.mapper_number);
+
+        std::fs::remove_file(&rom_path).ok();
+        std::fs::remove_file(&resaved_path).ok();
+    }
+
+    #[test]
+    fn test_battery_backed_prg_ram_saves_and_reloads_via_sav_file() {
+        let rom_path = std::env::temp_dir().join("rusty_nes_test_battery.nes");
+        let header_data: [u8; 16] = [
+            0x4E, 0x45, 0x53, 0x1A,
+            0x01, // 1 * 16KB PRG
+            0x01, // 1 * 8KB CHR
+            0x02, // Flags 6: battery-backed RAM
+            0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00
+        ];
+        let mut rom_bytes = header_data.to_vec();
+        rom_bytes.extend(std::iter::repeat_n(0xAB, 16 * 1024)); // PRG ROM
+        rom_bytes.extend(std::iter::repeat_n(0xCD, 8 * 1024)); // CHR ROM
+        std::fs::write(&rom_path, &rom_bytes).unwrap();
+        let sav_path = Cartridge::battery_save_path(rom_path.to_str().unwrap());
+        std::fs::remove_file(&sav_path).ok();
+
+        // Fresh load with no .sav file yet: PRG-RAM starts blank.
+        let mut cartridge = Cartridge::from_file(rom_path.to_str().unwrap()).unwrap();
+        assert!(cartridge.prg_ram.iter().all(|&b| b == 0));
+
+        // Mutate PRG-RAM and persist it to the sidecar .sav file.
+        cartridge.prg_ram[0] = 0x42;
+        cartridge.prg_ram[1] = 0x99;
+        cartridge.save_battery_ram().unwrap();
+        assert!(std::path::Path::new(&sav_path).exists());
+
+        // Reloading the same ROM must pick the saved contents back up.
+        let reloaded = Cartridge::from_file(rom_path.to_str().unwrap()).unwrap();
+        assert_eq!(reloaded.prg_ram[0], 0x42);
+        assert_eq!(reloaded.prg_ram[1], 0x99);
+
+        std::fs::remove_file(&rom_path).ok();
+        std::fs::remove_file(&sav_path).ok();
+    }
 } 
\ No newline at end of file
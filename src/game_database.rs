@@ -0,0 +1,97 @@
+use crate::cartridge::Mirroring;
+
+// Embedded known-good header overrides, keyed by CRC32 of PRG-ROM + CHR-ROM.
+const DATABASE: &str = include_str!("game_database.csv");
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GameDbEntry {
+    pub mapper_number: u16,
+    pub mirroring: Mirroring,
+    pub has_battery_backed_ram: bool,
+}
+
+/// CRC-32/ISO-HDLC: init 0xFFFFFFFF, reflected polynomial 0xEDB88320, final XOR 0xFFFFFFFF.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFFFFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc ^ 0xFFFFFFFF
+}
+
+/// Looks up a known-good header override for a ROM by its PRG+CHR CRC32.
+pub fn lookup(crc: u32) -> Option<GameDbEntry> {
+    lookup_in(DATABASE, crc)
+}
+
+fn lookup_in(database: &str, crc: u32) -> Option<GameDbEntry> {
+    for line in database.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // A malformed row must only skip itself: `?` here would propagate out
+        // of the whole function, silently hiding every entry after a typo.
+        let mut fields = line.split(',');
+        let Some(entry_crc_field) = fields.next() else { continue };
+        let Ok(entry_crc) = u32::from_str_radix(entry_crc_field, 16) else { continue };
+        if entry_crc != crc {
+            continue;
+        }
+
+        let Some(mapper_number) = fields.next().and_then(|f| f.parse::<u16>().ok()) else { continue };
+        let mirroring = match fields.next() {
+            Some("H") => Mirroring::Horizontal,
+            Some("V") => Mirroring::Vertical,
+            Some("F") => Mirroring::FourScreen,
+            _ => continue,
+        };
+        let Some(has_battery_backed_ram_field) = fields.next() else { continue };
+        let has_battery_backed_ram = has_battery_backed_ram_field == "1";
+
+        return Some(GameDbEntry { mapper_number, mirroring, has_battery_backed_ram });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_check_value() {
+        // The standard CRC-32/ISO-HDLC check value for the ASCII string "123456789".
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_lookup_known_entry() {
+        let entry = lookup(0xD6E4A8C7).expect("entry should be present in the embedded database");
+        assert_eq!(entry.mapper_number, 1);
+        assert_eq!(entry.mirroring, Mirroring::Vertical);
+        assert!(entry.has_battery_backed_ram);
+    }
+
+    #[test]
+    fn test_lookup_unknown_entry() {
+        assert!(lookup(0x00000000).is_none());
+    }
+
+    #[test]
+    fn test_lookup_skips_malformed_row_instead_of_aborting() {
+        let database = "BADLINE_not_hex,oops\nD6E4A8C7,1,V,1\n";
+        let entry = lookup_in(database, 0xD6E4A8C7)
+            .expect("a malformed row earlier in the file must not hide a later valid match");
+        assert_eq!(entry.mapper_number, 1);
+        assert_eq!(entry.mirroring, Mirroring::Vertical);
+        assert!(entry.has_battery_backed_ram);
+    }
+}
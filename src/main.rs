@@ -2,6 +2,8 @@ pub mod apu;
 pub mod bus;
 pub mod cartridge;
 pub mod cpu;
+pub mod game_database;
+pub mod mapper;
 pub mod ppu;
 
 fn main() {
@@ -0,0 +1,577 @@
+use crate::cartridge::{Cartridge, Mirroring};
+
+// Interprets CPU/PPU address space accesses through a cartridge's bank-switching
+// logic, for whatever mapper `from_cartridge` selects. `cpu_read`/`cpu_write`
+// take CPU addresses in $8000-$FFFF and `ppu_read`/`ppu_write` take PPU
+// addresses in $0000-$1FFF; `bus` and `ppu` do not call into this module.
+pub trait Mapper {
+    fn cpu_read(&mut self, addr: u16) -> u8;
+    fn cpu_write(&mut self, addr: u16, val: u8);
+    fn ppu_read(&mut self, addr: u16) -> u8;
+    fn ppu_write(&mut self, addr: u16, val: u8);
+    fn mirroring(&self) -> Mirroring;
+}
+
+// A crafted or merely corrupt ROM (e.g. a NES 2.0 exponent-multiplier size
+// that doesn't evenly divide a mapper's bank-switching unit) can leave PRG/CHR
+// memory smaller than the address range a mapper normally assumes. Reads past
+// the end return 0 (approximating real hardware's open-bus behavior) instead
+// of panicking.
+fn read_or_open_bus(data: &[u8], index: usize) -> u8 {
+    data.get(index).copied().unwrap_or(0)
+}
+
+pub fn from_cartridge(cartridge: &Cartridge) -> Box<dyn Mapper> {
+    match cartridge.header.mapper_number {
+        0 => Box::new(Nrom::new(cartridge)),
+        1 => Box::new(Mmc1::new(cartridge)),
+        2 => Box::new(Uxrom::new(cartridge)),
+        3 => Box::new(Cnrom::new(cartridge)),
+        4 => Box::new(Mmc3::new(cartridge)),
+        other => {
+            eprintln!("WARN: Mapper {} is not implemented, falling back to NROM", other);
+            Box::new(Nrom::new(cartridge))
+        }
+    }
+}
+
+struct Nrom {
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    fn new(cartridge: &Cartridge) -> Self {
+        Nrom {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr_rom: cartridge.chr_rom.clone(),
+            chr_is_ram: cartridge.chr_is_writable(),
+            mirroring: cartridge.header.mirroring,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&mut self, addr: u16) -> u8 {
+        // 16KB PRG-ROM is mirrored into both halves of $8000-$FFFF; 32KB fills it.
+        let index = (addr - 0x8000) as usize % self.prg_rom.len();
+        self.prg_rom[index]
+    }
+
+    fn cpu_write(&mut self, _addr: u16, _val: u8) {
+        // NROM has no registers; writes to PRG-ROM space are ignored.
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        read_or_open_bus(&self.chr_rom, addr as usize)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        if self.chr_is_ram && (addr as usize) < self.chr_rom.len() {
+            self.chr_rom[addr as usize] = val;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+struct Uxrom {
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    fn new(cartridge: &Cartridge) -> Self {
+        Uxrom {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr_rom: cartridge.chr_rom.clone(),
+            chr_is_ram: cartridge.chr_is_writable(),
+            mirroring: cartridge.header.mirroring,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&mut self, addr: u16) -> u8 {
+        // .max(1) guards a PRG-ROM smaller than one 16KB bank (e.g. a corrupt
+        // or crafted NES 2.0 dump) against a division/subtraction panic.
+        let bank_count = (self.prg_rom.len() / 0x4000).max(1);
+        match addr {
+            // $8000-$BFFF: 16KB bank selected by the last CPU write.
+            0x8000..=0xBFFF => {
+                let bank = self.prg_bank as usize % bank_count;
+                read_or_open_bus(&self.prg_rom, bank * 0x4000 + (addr - 0x8000) as usize)
+            }
+            // $C000-$FFFF: fixed to the last 16KB bank.
+            _ => {
+                let last_bank = bank_count - 1;
+                read_or_open_bus(&self.prg_rom, last_bank * 0x4000 + (addr - 0xC000) as usize)
+            }
+        }
+    }
+
+    fn cpu_write(&mut self, _addr: u16, val: u8) {
+        self.prg_bank = val & 0x0F;
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        read_or_open_bus(&self.chr_rom, addr as usize)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        if self.chr_is_ram && (addr as usize) < self.chr_rom.len() {
+            self.chr_rom[addr as usize] = val;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+struct Cnrom {
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    fn new(cartridge: &Cartridge) -> Self {
+        Cnrom {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr_rom: cartridge.chr_rom.clone(),
+            chr_is_ram: cartridge.chr_is_writable(),
+            mirroring: cartridge.header.mirroring,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&mut self, addr: u16) -> u8 {
+        let index = (addr - 0x8000) as usize % self.prg_rom.len();
+        self.prg_rom[index]
+    }
+
+    fn cpu_write(&mut self, _addr: u16, val: u8) {
+        self.chr_bank = val & 0x03;
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let bank = self.chr_bank as usize % (self.chr_rom.len() / 0x2000).max(1);
+        read_or_open_bus(&self.chr_rom, bank * 0x2000 + addr as usize)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        if self.chr_is_ram && (addr as usize) < self.chr_rom.len() {
+            self.chr_rom[addr as usize] = val;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+struct Mmc1 {
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    shift_register: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank0: u8,
+    chr_bank1: u8,
+    prg_bank: u8,
+}
+
+impl Mmc1 {
+    fn new(cartridge: &Cartridge) -> Self {
+        Mmc1 {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr_rom: cartridge.chr_rom.clone(),
+            chr_is_ram: cartridge.chr_is_writable(),
+            mirroring: cartridge.header.mirroring,
+            shift_register: 0,
+            shift_count: 0,
+            control: 0x0C, // Power-on default: PRG mode 3 (fix last bank at $C000)
+            chr_bank0: 0,
+            chr_bank1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        // .max(1) guards a PRG-ROM smaller than one 16KB bank (e.g. a corrupt
+        // or crafted NES 2.0 dump) against a division/subtraction panic.
+        (self.prg_rom.len() / 0x4000).max(1)
+    }
+
+    fn chr_bank_count_4k(&self) -> usize {
+        (self.chr_rom.len() / 0x1000).max(1)
+    }
+
+    // A write with bit 7 set resets the shift register and forces PRG mode 3;
+    // otherwise each write shifts one bit in until five have arrived, at which
+    // point the accumulated value is latched into the register addr selects.
+    fn write_serial(&mut self, addr: u16, val: u8) {
+        if val & 0x80 != 0 {
+            self.shift_register = 0;
+            self.shift_count = 0;
+            self.control |= 0x0C;
+            return;
+        }
+
+        self.shift_register |= (val & 0x01) << self.shift_count;
+        self.shift_count += 1;
+
+        if self.shift_count == 5 {
+            let result = self.shift_register;
+            match addr {
+                0x8000..=0x9FFF => self.control = result,
+                0xA000..=0xBFFF => self.chr_bank0 = result,
+                0xC000..=0xDFFF => self.chr_bank1 = result,
+                _ => self.prg_bank = result & 0x0F,
+            }
+            self.shift_register = 0;
+            self.shift_count = 0;
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&mut self, addr: u16) -> u8 {
+        let prg_mode = (self.control >> 2) & 0x03;
+        let bank_count = self.prg_bank_count();
+        let offset = (addr - 0x8000) as usize;
+
+        let (bank, bank_offset) = match prg_mode {
+            // 32KB mode: ignore the low bit of the selected bank.
+            0 | 1 => ((self.prg_bank as usize & 0x0E) >> 1, offset),
+            // Fix first bank at $8000, switch 16KB at $C000.
+            2 if addr < 0xC000 => (0, offset),
+            2 => (self.prg_bank as usize, offset - 0x4000),
+            // Switch 16KB at $8000, fix last bank at $C000.
+            3 if addr < 0xC000 => (self.prg_bank as usize, offset),
+            _ => (bank_count - 1, offset - 0x4000),
+        };
+
+        let window = if prg_mode <= 1 { 0x8000 } else { 0x4000 };
+        read_or_open_bus(&self.prg_rom, (bank % bank_count) * window.min(self.prg_rom.len()) + bank_offset)
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        self.write_serial(addr, val);
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let chr_4k_mode = self.control & 0x10 != 0;
+        let bank_count = self.chr_bank_count_4k();
+
+        let (bank, offset) = if chr_4k_mode {
+            if addr < 0x1000 {
+                (self.chr_bank0 as usize, addr as usize)
+            } else {
+                (self.chr_bank1 as usize, addr as usize - 0x1000)
+            }
+        } else {
+            // 8KB mode: ignore the low bit, address spans both 4KB banks.
+            ((self.chr_bank0 as usize) >> 1, addr as usize)
+        };
+
+        let window = if chr_4k_mode { 0x1000 } else { 0x2000 };
+        read_or_open_bus(&self.chr_rom, (bank % bank_count) * window.min(self.chr_rom.len()) + offset)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        if self.chr_is_ram && (addr as usize) < self.chr_rom.len() {
+            self.chr_rom[addr as usize] = val;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 | 1 => self.mirroring, // One-screen modes: fall back to the header's mirroring.
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+}
+
+struct Mmc3 {
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bank_select: u8,
+    bank_registers: [u8; 8],
+}
+
+impl Mmc3 {
+    fn new(cartridge: &Cartridge) -> Self {
+        Mmc3 {
+            prg_rom: cartridge.prg_rom.clone(),
+            chr_rom: cartridge.chr_rom.clone(),
+            chr_is_ram: cartridge.chr_is_writable(),
+            mirroring: cartridge.header.mirroring,
+            bank_select: 0,
+            bank_registers: [0; 8],
+        }
+    }
+
+    fn prg_bank_count_8k(&self) -> usize {
+        // .max(1) guards a PRG-ROM smaller than one 8KB bank (e.g. a corrupt
+        // or crafted NES 2.0 dump) against a division/subtraction panic.
+        (self.prg_rom.len() / 0x2000).max(1)
+    }
+
+    fn chr_bank_count_1k(&self) -> usize {
+        (self.chr_rom.len() / 0x400).max(1)
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_read(&mut self, addr: u16) -> u8 {
+        let bank_count = self.prg_bank_count_8k();
+        let prg_mode = self.bank_select & 0x40 != 0;
+        let slot = (addr - 0x8000) as usize / 0x2000;
+        let offset = (addr as usize) % 0x2000;
+
+        // Bank 6 is either switchable ($8000) or fixed to the second-to-last
+        // bank, depending on PRG mode; bank 7 is always switchable. Saturating
+        // subtraction covers a PRG-ROM smaller than 2 banks.
+        let bank = match slot {
+            0 if prg_mode => bank_count.saturating_sub(2),
+            0 => self.bank_registers[6] as usize,
+            1 => self.bank_registers[7] as usize,
+            2 if prg_mode => self.bank_registers[6] as usize,
+            2 => bank_count.saturating_sub(2),
+            _ => bank_count.saturating_sub(1),
+        };
+
+        read_or_open_bus(&self.prg_rom, (bank % bank_count) * 0x2000 + offset)
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        let even = addr.is_multiple_of(2);
+        match (addr, even) {
+            (0x8000..=0x9FFF, true) => self.bank_select = val,
+            (0x8000..=0x9FFF, false) => {
+                let register = (self.bank_select & 0x07) as usize;
+                self.bank_registers[register] = val;
+            }
+            (0xA000..=0xBFFF, true) => {
+                self.mirroring = if val & 0x01 != 0 { Mirroring::Horizontal } else { Mirroring::Vertical };
+            }
+            // PRG-RAM protect and IRQ registers are not yet modeled.
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let bank_count = self.chr_bank_count_1k();
+        let chr_mode = self.bank_select & 0x80 != 0;
+
+        // Normal mode: two 2KB banks then four 1KB banks; mode bit inverts
+        // which half of the 8KB window each group occupies.
+        let regions: [(u16, usize, u16); 6] = if !chr_mode {
+            [
+                (0x0000, (self.bank_registers[0] & 0xFE) as usize, 0x0800),
+                (0x0800, (self.bank_registers[1] & 0xFE) as usize, 0x0800),
+                (0x1000, self.bank_registers[2] as usize, 0x0400),
+                (0x1400, self.bank_registers[3] as usize, 0x0400),
+                (0x1800, self.bank_registers[4] as usize, 0x0400),
+                (0x1C00, self.bank_registers[5] as usize, 0x0400),
+            ]
+        } else {
+            [
+                (0x0000, self.bank_registers[2] as usize, 0x0400),
+                (0x0400, self.bank_registers[3] as usize, 0x0400),
+                (0x0800, self.bank_registers[4] as usize, 0x0400),
+                (0x0C00, self.bank_registers[5] as usize, 0x0400),
+                (0x1000, (self.bank_registers[0] & 0xFE) as usize, 0x0800),
+                (0x1800, (self.bank_registers[1] & 0xFE) as usize, 0x0800),
+            ]
+        };
+
+        for (base, bank, size) in regions {
+            if addr >= base && addr < base + size {
+                let offset = (addr - base) as usize;
+                return read_or_open_bus(&self.chr_rom, (bank % bank_count) * 0x400 + offset);
+            }
+        }
+        unreachable!("PPU address out of CHR range: {:#06X}", addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        if self.chr_is_ram && (addr as usize) < self.chr_rom.len() {
+            self.chr_rom[addr as usize] = val;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::Cartridge;
+
+    // Builds a minimal iNES image for the given mapper number around already
+    // prepared PRG/CHR data, so tests can tag specific banks/offsets and
+    // assert on them through the Mapper trait.
+    fn build_ines_bytes(mapper_number: u8, prg: &[u8], chr: &[u8]) -> Vec<u8> {
+        let prg_banks = (prg.len() / (16 * 1024)) as u8;
+        let chr_banks = (chr.len() / (8 * 1024)) as u8;
+        let flags6 = (mapper_number & 0x0F) << 4;
+        let flags7 = mapper_number & 0xF0;
+        let mut bytes = vec![
+            0x4E, 0x45, 0x53, 0x1A,
+            prg_banks, chr_banks,
+            flags6, flags7,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        bytes.extend_from_slice(prg);
+        bytes.extend_from_slice(chr);
+        bytes
+    }
+
+    fn write_serial(mapper: &mut dyn Mapper, addr: u16, value: u8) {
+        for i in 0..5u8 {
+            mapper.cpu_write(addr, (value >> i) & 0x01);
+        }
+    }
+
+    #[test]
+    fn test_nrom_mirrors_16kb_prg_into_both_cpu_halves() {
+        let mut prg = vec![0u8; 16 * 1024];
+        prg[0] = 0x11;
+        prg[0x3FFF] = 0x22;
+        let chr = vec![0u8; 8 * 1024];
+        let bytes = build_ines_bytes(0, &prg, &chr);
+        let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+        let mut mapper = from_cartridge(&cartridge);
+
+        assert_eq!(mapper.cpu_read(0x8000), 0x11);
+        assert_eq!(mapper.cpu_read(0xBFFF), 0x22);
+        assert_eq!(mapper.cpu_read(0xC000), 0x11); // mirrored into the upper half
+        assert_eq!(mapper.cpu_read(0xFFFF), 0x22);
+    }
+
+    #[test]
+    fn test_uxrom_switches_low_bank_and_fixes_last_bank() {
+        // 4 banks of 16KB, each tagged at its first byte with its own index.
+        let mut prg = vec![0u8; 4 * 0x4000];
+        for bank in 0..4u8 {
+            prg[bank as usize * 0x4000] = 0xA0 + bank;
+        }
+        let chr = vec![0u8; 8 * 1024];
+        let bytes = build_ines_bytes(2, &prg, &chr);
+        let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+        let mut mapper = from_cartridge(&cartridge);
+
+        // Bank register defaults to 0 before any CPU write.
+        assert_eq!(mapper.cpu_read(0x8000), 0xA0);
+        // $C000-$FFFF is always fixed to the last bank, regardless of selection.
+        assert_eq!(mapper.cpu_read(0xC000), 0xA3);
+
+        mapper.cpu_write(0x8000, 2);
+        assert_eq!(mapper.cpu_read(0x8000), 0xA2);
+        assert_eq!(mapper.cpu_read(0xC000), 0xA3);
+    }
+
+    #[test]
+    fn test_mmc1_serial_shift_writes_and_mid_sequence_reset() {
+        let mut prg = vec![0u8; 4 * 0x4000];
+        for bank in 0..4u8 {
+            prg[bank as usize * 0x4000] = 0xB0 + bank;
+        }
+        let chr = vec![0u8; 8 * 1024];
+        let bytes = build_ines_bytes(1, &prg, &chr);
+        let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+        let mut mapper = from_cartridge(&cartridge);
+
+        // Power-on default (control = 0x0C, PRG mode 3): $8000 switchable
+        // (bank register defaults to 0), $C000 fixed to the last bank.
+        assert_eq!(mapper.cpu_read(0x8000), 0xB0);
+        assert_eq!(mapper.cpu_read(0xC000), 0xB3);
+
+        // Start a 5-write sequence selecting PRG bank 1, but reset after 2
+        // bits: the reset must discard the partial shift rather than corrupt
+        // whatever sequence comes next.
+        mapper.cpu_write(0xE000, 1);
+        mapper.cpu_write(0xE000, 0);
+        mapper.cpu_write(0xE000, 0x80); // bit 7 set: reset
+
+        // A full, uninterrupted 5-write sequence selecting PRG bank 2.
+        write_serial(&mut *mapper, 0xE000, 2);
+
+        assert_eq!(mapper.cpu_read(0x8000), 0xB2);
+        assert_eq!(mapper.cpu_read(0xC000), 0xB3); // fixed bank unaffected
+    }
+
+    #[test]
+    fn test_mmc1_32kb_prg_mode_and_4kb_chr_mode() {
+        // One 32KB PRG "bank pair", tagged at each 16KB half.
+        let mut prg = vec![0u8; 2 * 0x4000];
+        prg[0] = 0x01;
+        prg[0x4000] = 0x02;
+        // Two 4KB CHR banks, tagged at each half.
+        let mut chr = vec![0u8; 8 * 1024];
+        chr[0] = 0x10;
+        chr[0x1000] = 0x20;
+        let bytes = build_ines_bytes(1, &prg, &chr);
+        let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+        let mut mapper = from_cartridge(&cartridge);
+
+        // Control: bit 0x10 set (CHR 4KB mode), PRG mode bits 0 (32KB mode).
+        write_serial(&mut *mapper, 0x8000, 0b1_0000);
+        assert_eq!(mapper.cpu_read(0x8000), 0x01);
+        assert_eq!(mapper.cpu_read(0xC000), 0x02);
+
+        // Select CHR bank 1 for $0000-$0FFF.
+        write_serial(&mut *mapper, 0xA000, 1);
+        assert_eq!(mapper.ppu_read(0x0000), 0x20);
+    }
+
+    #[test]
+    fn test_mmc3_bank_select_parity_and_prg_mode_inversion() {
+        // 4 banks of 8KB, each tagged at its first byte with its own index.
+        let mut prg = vec![0u8; 4 * 0x2000];
+        for bank in 0..4u8 {
+            prg[bank as usize * 0x2000] = 0xC0 + bank;
+        }
+        let chr = vec![0u8; 8 * 1024];
+        let bytes = build_ines_bytes(4, &prg, &chr);
+        let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+        let mut mapper = from_cartridge(&cartridge);
+
+        // Even address selects register R6; the following odd-address write
+        // lands in bank_registers[6] regardless of which odd address is used.
+        mapper.cpu_write(0x8000, 6);
+        mapper.cpu_write(0x8001, 1);
+
+        // Default PRG mode: $8000 is switchable (R6 = bank 1), $C000 is fixed
+        // to the second-to-last bank (bank 2 of 4).
+        assert_eq!(mapper.cpu_read(0x8000), 0xC1);
+        assert_eq!(mapper.cpu_read(0xC000), 0xC2);
+
+        // Flipping the PRG mode bit swaps which slot is fixed vs. switchable.
+        mapper.cpu_write(0x8000, 0x40 | 6);
+        assert_eq!(mapper.cpu_read(0x8000), 0xC2);
+        assert_eq!(mapper.cpu_read(0xC000), 0xC1);
+    }
+}